@@ -1,12 +1,34 @@
-use rusqlite::{Connection, OptionalExtension};
+use once_cell::sync::Lazy;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use tauri::{AppHandle, Manager};
 use xxhash_rust::xxh3::xxh3_64;
 
 const CACHE_FILENAME: &str = "translations.sqlite3";
 
-fn cache_db_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+/// The cache and history schemas share one database file, so the pool
+/// (and the schema it initializes) is keyed by that single path.
+static CACHE_POOL: Lazy<Mutex<Option<Pool<SqliteConnectionManager>>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Copy)]
+struct CachePolicy {
+    max_age_secs: Option<i64>,
+    max_entries: Option<i64>,
+}
+
+static CACHE_POLICY: Lazy<Mutex<CachePolicy>> = Lazy::new(|| {
+    Mutex::new(CachePolicy {
+        max_age_secs: None,
+        max_entries: None,
+    })
+});
+
+pub(crate) fn cache_db_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
     let mut dir = app.path().app_data_dir()?;
     dir.push("cache");
     fs::create_dir_all(&dir)?;
@@ -24,28 +46,100 @@ fn init_cache_schema(conn: &Connection) -> rusqlite::Result<()> {
         )",
         [],
     )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_translations_created_at ON translations(created_at)",
+        [],
+    )?;
     Ok(())
 }
 
+/// Opens (once) or reuses the pooled connection to the cache/history
+/// database, creating the schema on first use. The commands call this in
+/// their async body before entering `spawn_blocking`, so the first call
+/// builds the pool on the async runtime; only the connections it hands out
+/// are used inside the blocking tasks.
+pub(crate) fn cache_pool(app: &AppHandle) -> Result<Pool<SqliteConnectionManager>, String> {
+    let mut pool = CACHE_POOL.lock().unwrap();
+    if let Some(pool) = pool.as_ref() {
+        return Ok(pool.clone());
+    }
+
+    let path = cache_db_path(app).map_err(|e| e.to_string())?;
+    let manager = SqliteConnectionManager::file(path);
+    let built = Pool::new(manager).map_err(|e| e.to_string())?;
+    {
+        let conn = built.get().map_err(|e| e.to_string())?;
+        init_cache_schema(&conn).map_err(|e| e.to_string())?;
+    }
+    *pool = Some(built.clone());
+    Ok(built)
+}
+
 fn cache_key(text: &str) -> String {
     format!("{:016x}", xxh3_64(text.as_bytes()))
 }
 
+fn now_secs() -> Result<i64, String> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .map_err(|e| e.to_string())
+}
+
+/// The oldest `created_at` a lookup will still honor, given the current
+/// policy. `i64::MIN` when no max age is set, so the `created_at >= ?`
+/// filter is a no-op.
+fn min_created_at(policy: CachePolicy, now: i64) -> i64 {
+    match policy.max_age_secs {
+        Some(max_age) => now - max_age,
+        None => i64::MIN,
+    }
+}
+
+/// Deletes the oldest rows beyond `max_entries`, if a limit is set.
+fn prune_cache(conn: &Connection, max_entries: Option<i64>) -> rusqlite::Result<()> {
+    let Some(max_entries) = max_entries else {
+        return Ok(());
+    };
+    conn.execute(
+        "DELETE FROM translations WHERE key IN (
+            SELECT key FROM translations ORDER BY created_at ASC
+            LIMIT MAX(0, (SELECT COUNT(*) FROM translations) - ?1)
+        )",
+        [max_entries],
+    )?;
+    Ok(())
+}
+
+/// Sets how long a cached translation stays valid and how many rows the
+/// cache keeps. Either bound can be disabled by passing `None`.
+#[tauri::command]
+pub fn set_cache_policy(max_age_secs: Option<i64>, max_entries: Option<i64>) {
+    *CACHE_POLICY.lock().unwrap() = CachePolicy {
+        max_age_secs,
+        max_entries,
+    };
+}
+
 #[tauri::command]
 pub async fn get_cached_translation(
     app: AppHandle,
     text: String,
 ) -> Result<Option<String>, String> {
-    let path = cache_db_path(&app).map_err(|e| e.to_string())?;
+    let pool = cache_pool(&app)?;
+    let policy = *CACHE_POLICY.lock().unwrap();
     tauri::async_runtime::spawn_blocking(move || {
-        let conn = Connection::open(path).map_err(|e| e.to_string())?;
-        init_cache_schema(&conn).map_err(|e| e.to_string())?;
+        let conn = pool.get().map_err(|e| e.to_string())?;
         let key = cache_key(&text);
+        let min_created_at = min_created_at(policy, now_secs()?);
         let mut stmt = conn
-            .prepare("SELECT translation FROM translations WHERE key = ?1 LIMIT 1")
+            .prepare(
+                "SELECT translation FROM translations
+                WHERE key = ?1 AND created_at >= ?2 LIMIT 1",
+            )
             .map_err(|e| e.to_string())?;
         let translation = stmt
-            .query_row([key], |row| row.get::<_, String>(0))
+            .query_row((key, min_created_at), |row| row.get::<_, String>(0))
             .optional()
             .map_err(|e| e.to_string())?;
         Ok::<_, String>(translation)
@@ -64,15 +158,12 @@ pub async fn store_translation(
         return Ok(());
     }
 
-    let path = cache_db_path(&app).map_err(|e| e.to_string())?;
+    let pool = cache_pool(&app)?;
+    let policy = *CACHE_POLICY.lock().unwrap();
     tauri::async_runtime::spawn_blocking(move || {
-        let conn = Connection::open(path).map_err(|e| e.to_string())?;
-        init_cache_schema(&conn).map_err(|e| e.to_string())?;
+        let conn = pool.get().map_err(|e| e.to_string())?;
         let key = cache_key(&text);
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| e.to_string())?
-            .as_secs() as i64;
+        let now = now_secs()?;
 
         conn.execute(
             "INSERT OR REPLACE INTO translations (key, original, translation, created_at)
@@ -80,6 +171,80 @@ pub async fn store_translation(
             (&key, &text, &translation, now),
         )
         .map_err(|e| e.to_string())?;
+        prune_cache(&conn, policy.max_entries).map_err(|e| e.to_string())?;
+        Ok::<_, String>(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Looks up many texts in one connection, preserving input order (each
+/// result lines up with the input at the same index).
+#[tauri::command]
+pub async fn get_cached_translations(
+    app: AppHandle,
+    texts: Vec<String>,
+) -> Result<Vec<Option<String>>, String> {
+    let pool = cache_pool(&app)?;
+    let policy = *CACHE_POLICY.lock().unwrap();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| e.to_string())?;
+        let min_created_at = min_created_at(policy, now_secs()?);
+
+        let keys: Vec<String> = texts.iter().map(|text| cache_key(text)).collect();
+        let placeholders = keys.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+        let sql = format!(
+            "SELECT key, translation FROM translations
+            WHERE created_at >= ? AND key IN ({placeholders})"
+        );
+
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut found: HashMap<String, String> = HashMap::new();
+        let params = std::iter::once(min_created_at.to_string()).chain(keys.iter().cloned());
+        let rows = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (key, translation) = row.map_err(|e| e.to_string())?;
+            found.insert(key, translation);
+        }
+
+        Ok::<_, String>(keys.iter().map(|key| found.get(key).cloned()).collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Stores many (original, translation) pairs in one connection/transaction.
+#[tauri::command]
+pub async fn store_translations(
+    app: AppHandle,
+    pairs: Vec<(String, String)>,
+) -> Result<(), String> {
+    let pool = cache_pool(&app)?;
+    let policy = *CACHE_POLICY.lock().unwrap();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        let now = now_secs()?;
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (text, translation) in pairs {
+            if translation.trim().is_empty() {
+                continue;
+            }
+            let key = cache_key(&text);
+            tx.execute(
+                "INSERT OR REPLACE INTO translations (key, original, translation, created_at)
+                VALUES (?1, ?2, ?3, ?4)",
+                (&key, &text, &translation, now),
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        prune_cache(&conn, policy.max_entries).map_err(|e| e.to_string())?;
+
         Ok::<_, String>(())
     })
     .await
@@ -1,3 +1,4 @@
+use crate::language::should_emit;
 use crate::text_replacements::apply_text_replacements;
 use arboard::Clipboard;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -8,7 +9,7 @@ use tokio::time::sleep;
 
 static CLIPBOARD_ENABLED: AtomicBool = AtomicBool::new(false);
 
-async fn poll_clipboard_text() -> anyhow::Result<Option<String>> {
+pub(crate) async fn poll_clipboard_text() -> anyhow::Result<Option<String>> {
     let text = spawn_blocking(|| -> anyhow::Result<Option<String>> {
         let mut clipboard = Clipboard::new()?;
         Ok(clipboard.get_text().ok())
@@ -31,6 +32,11 @@ pub fn start_clipboard_watcher(app: AppHandle) {
 
             match poll_clipboard_text().await {
                 Ok(Some(text)) if !text.is_empty() => {
+                    if !should_emit(&text) {
+                        sleep(Duration::from_millis(1500)).await;
+                        continue;
+                    }
+
                     let processed = apply_text_replacements(&text);
                     if processed.is_empty() || processed == last {
                         sleep(Duration::from_millis(1500)).await;
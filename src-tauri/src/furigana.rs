@@ -1,29 +1,61 @@
+use crate::language::is_japanese_text;
 use lindera::{
-    dictionary::load_dictionary, mode::Mode, segmenter::Segmenter, token::Token,
+    dictionary::{load_dictionary, load_user_dictionary, UserDictionary},
+    mode::Mode,
+    segmenter::Segmenter,
+    token::Token,
     tokenizer::Tokenizer,
 };
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use tauri::{AppHandle, Manager};
 
 static TOKENIZER: OnceLock<Mutex<Tokenizer>> = OnceLock::new();
 
-fn dictionary_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut path = app
-        .path()
+fn dictionary_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
         .app_data_dir()
-        .map_err(|err| format!("failed to resolve app data dir: {err}"))?;
+        .map_err(|err| format!("failed to resolve app data dir: {err}"))
+}
+
+fn dictionary_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut path = dictionary_dir(app)?;
     path.push("dictionary");
     path.push("unidic");
     Ok(path)
 }
 
-fn initialize_tokenizer(app: &AppHandle) -> Result<(), String> {
+fn user_dictionary_csv_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut path = dictionary_dir(app)?;
+    path.push("dictionary");
+    path.push("user.csv");
+    Ok(path)
+}
+
+fn load_user_dictionary_if_present(path: &Path) -> Result<Option<UserDictionary>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    load_user_dictionary(path)
+        .map(Some)
+        .map_err(|err| format!("failed to load user dictionary: {err}"))
+}
+
+fn build_segmenter(app: &AppHandle) -> Result<Segmenter, String> {
     let path = dictionary_path(app)?;
     let dictionary_uri = format!("file://{}", path.to_string_lossy());
     let dictionary = load_dictionary(&dictionary_uri)
         .map_err(|err| format!("failed to load dictionary: {err}"))?;
-    let segmenter = Segmenter::new(Mode::Normal, dictionary, None);
+    let user_dictionary = load_user_dictionary_if_present(&user_dictionary_csv_path(app)?)?;
+
+    Ok(Segmenter::new(Mode::Normal, dictionary, user_dictionary))
+}
+
+fn initialize_tokenizer(app: &AppHandle) -> Result<(), String> {
+    let segmenter = build_segmenter(app)?;
     let tokenizer = Mutex::new(Tokenizer::new(segmenter));
 
     TOKENIZER
@@ -31,6 +63,26 @@ fn initialize_tokenizer(app: &AppHandle) -> Result<(), String> {
         .map_err(|_| "tokenizer already initialized".to_string())
 }
 
+/// Rebuilds the segmenter from the current user dictionary CSV and swaps it
+/// into the live tokenizer, so dictionary edits take effect without a restart.
+fn reload_tokenizer(app: &AppHandle) -> Result<(), String> {
+    let segmenter = build_segmenter(app)?;
+    let rebuilt = Tokenizer::new(segmenter);
+
+    match TOKENIZER.get() {
+        Some(existing) => {
+            let mut guard = existing
+                .lock()
+                .map_err(|err| format!("tokenizer lock poisoned: {err}"))?;
+            *guard = rebuilt;
+            Ok(())
+        }
+        None => TOKENIZER
+            .set(Mutex::new(rebuilt))
+            .map_err(|_| "tokenizer already initialized".to_string()),
+    }
+}
+
 fn tokenizer(app: &AppHandle) -> Result<std::sync::MutexGuard<'static, Tokenizer>, String> {
     if TOKENIZER.get().is_none() {
         initialize_tokenizer(app)?;
@@ -43,6 +95,64 @@ fn tokenizer(app: &AppHandle) -> Result<std::sync::MutexGuard<'static, Tokenizer
         .map_err(|err| format!("tokenizer lock poisoned: {err}"))
 }
 
+#[derive(Deserialize, Debug)]
+pub struct UserDictionaryEntry {
+    pub surface: String,
+    pub reading: String,
+    pub pos: String,
+}
+
+fn read_user_dictionary_rows(path: &Path) -> Vec<(String, String, String)> {
+    let Ok(existing) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    existing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ',');
+            let surface = fields.next()?;
+            let reading = fields.next()?;
+            let pos = fields.next()?;
+            Some((surface.to_string(), reading.to_string(), pos.to_string()))
+        })
+        .collect()
+}
+
+fn merge_user_dictionary_csv(path: &Path, entries: Vec<UserDictionaryEntry>) -> Result<(), String> {
+    let mut rows = read_user_dictionary_rows(path);
+
+    for entry in entries {
+        match rows.iter_mut().find(|(surface, _, _)| *surface == entry.surface) {
+            Some(existing) => *existing = (entry.surface, entry.reading, entry.pos),
+            None => rows.push((entry.surface, entry.reading, entry.pos)),
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("failed to create dictionary dir: {err}"))?;
+    }
+
+    let mut file =
+        fs::File::create(path).map_err(|err| format!("failed to write user dictionary: {err}"))?;
+    for (surface, reading, pos) in rows {
+        writeln!(file, "{surface},{reading},{pos}")
+            .map_err(|err| format!("failed to write user dictionary: {err}"))?;
+    }
+
+    Ok(())
+}
+
+/// Merges `entries` into the user dictionary CSV (keyed by surface) and
+/// hot-swaps the live tokenizer so corrected readings apply immediately.
+#[tauri::command]
+pub fn set_user_dictionary(app: AppHandle, entries: Vec<UserDictionaryEntry>) -> Result<(), String> {
+    let csv_path = user_dictionary_csv_path(&app)?;
+    merge_user_dictionary_csv(&csv_path, entries)?;
+    reload_tokenizer(&app)
+}
+
 fn escape_html(input: &str) -> String {
     let mut escaped = String::with_capacity(input.len());
     for ch in input.chars() {
@@ -83,11 +193,132 @@ fn token_reading(token: &mut Token) -> Option<String> {
     })
 }
 
-fn annotate_with_furigana(app: &AppHandle, text: &str) -> Result<String, String> {
+// UniDic's accent-type (aType) column, counted past the part-of-speech,
+// conjugation and lemma/reading fields `get_detail` already exposes.
+const ACCENT_TYPE_DETAIL_INDEX: usize = 24;
+
+fn token_accent_type(token: &mut Token) -> Option<u32> {
+    token.get_detail(ACCENT_TYPE_DETAIL_INDEX).and_then(|raw| {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed == "*" {
+            None
+        } else {
+            trimmed.parse::<u32>().ok()
+        }
+    })
+}
+
+/// Counts morae in a hiragana reading: small vowels and small ya/yu/yo
+/// combine with the preceding kana into a single mora instead of adding one.
+fn mora_count(reading: &str) -> usize {
+    const COMBINING_SMALL_KANA: &str = "ゃゅょぁぃぅぇぉ";
+    reading
+        .chars()
+        .filter(|c| !COMBINING_SMALL_KANA.contains(*c))
+        .count()
+}
+
+/// Measures a token's surface/reading pair and returns `(prefix, suffix)`:
+/// the lengths of the leading and trailing kana runs that already match
+/// the reading. The differing middle span is whatever lies between them.
+/// Lengths are char counts, not bytes,
+/// and the prefix/suffix never overlap (a fully-kana surface collapses
+/// to an empty middle).
+fn okurigana_split(normalized_surface: &str, reading: &str) -> (usize, usize) {
+    let surface_chars: Vec<char> = normalized_surface.chars().collect();
+    let reading_chars: Vec<char> = reading.chars().collect();
+    let max_len = surface_chars.len().min(reading_chars.len());
+
+    let mut prefix = 0;
+    while prefix < max_len && surface_chars[prefix] == reading_chars[prefix] {
+        prefix += 1;
+    }
+
+    let mut suffix = 0;
+    while suffix < max_len - prefix
+        && surface_chars[surface_chars.len() - 1 - suffix] == reading_chars[reading_chars.len() - 1 - suffix]
+    {
+        suffix += 1;
+    }
+
+    (prefix, suffix)
+}
+
+/// Builds the attributes to splice into a `<ruby ...>` tag so the mora
+/// where pitch drops can be targeted in CSS: `data-accent="N"` for the raw
+/// UniDic accent type (0 = flat/heiban, no downstep) plus a
+/// `pitch-drop-mora-N` class naming the mora index of the downstep (type 0
+/// gets a `pitch-heiban` class instead).
+fn accent_attrs(reading: &str, accent_type: u32) -> String {
+    if accent_type == 0 {
+        return " data-accent=\"0\" class=\"pitch-heiban\"".to_string();
+    }
+    let mora = mora_count(reading).min(accent_type as usize);
+    format!(" data-accent=\"{accent_type}\" class=\"pitch-drop-mora-{mora}\"")
+}
+
+fn push_ruby(annotated: &mut String, surface: &str, reading: &str, accent: Option<&str>) {
+    let accent_attrs = accent.unwrap_or("");
+
+    if reading == surface {
+        annotated.push_str(&escape_html(surface));
+        return;
+    }
+
+    let normalized_surface = katakana_to_hiragana(surface);
+    let (prefix, suffix) = okurigana_split(&normalized_surface, reading);
+
+    let surface_chars: Vec<char> = surface.chars().collect();
+    let reading_chars: Vec<char> = reading.chars().collect();
+
+    if prefix + suffix >= surface_chars.len() {
+        // Entire surface is kana and matched the reading outright.
+        annotated.push_str(&escape_html(surface));
+        return;
+    }
+
+    if prefix == 0 && suffix == 0 {
+        // No aligned kana at either edge; fall back to wrapping the whole token.
+        annotated.push_str("<ruby");
+        annotated.push_str(accent_attrs);
+        annotated.push('>');
+        annotated.push_str(&escape_html(surface));
+        annotated.push_str("<rt>");
+        annotated.push_str(&escape_html(reading));
+        annotated.push_str("</rt></ruby>");
+        return;
+    }
+
+    let prefix_str: String = surface_chars[..prefix].iter().collect();
+    let middle_str: String = surface_chars[prefix..surface_chars.len() - suffix]
+        .iter()
+        .collect();
+    let reading_middle: String = reading_chars[prefix..reading_chars.len() - suffix]
+        .iter()
+        .collect();
+
+    annotated.push_str(&escape_html(&prefix_str));
+    annotated.push_str("<ruby");
+    annotated.push_str(accent_attrs);
+    annotated.push('>');
+    annotated.push_str(&escape_html(&middle_str));
+    annotated.push_str("<rt>");
+    annotated.push_str(&escape_html(&reading_middle));
+    annotated.push_str("</rt></ruby>");
+    for ch in surface_chars[surface_chars.len() - suffix..].iter() {
+        annotated.push_str(&escape_html(&ch.to_string()));
+    }
+}
+
+fn annotate_with_furigana(app: &AppHandle, text: &str, accent_mode: bool) -> Result<String, String> {
     if text.trim().is_empty() {
         return Ok(String::new());
     }
 
+    if !is_japanese_text(text) {
+        return Ok(escape_html(text));
+    }
+
     let tokenizer = tokenizer(app)?;
     let mut tokens = tokenizer
         .tokenize(text)
@@ -105,15 +336,12 @@ fn annotate_with_furigana(app: &AppHandle, text: &str) -> Result<String, String>
         let surface = &text[start..end];
 
         if let Some(reading) = token_reading(token) {
-            if reading == surface {
-                annotated.push_str(&escape_html(surface));
+            let accent = if accent_mode {
+                token_accent_type(token).map(|accent_type| accent_attrs(&reading, accent_type))
             } else {
-                annotated.push_str("<ruby>");
-                annotated.push_str(&escape_html(surface));
-                annotated.push_str("<rt>");
-                annotated.push_str(&escape_html(&reading));
-                annotated.push_str("</rt></ruby>");
-            }
+                None
+            };
+            push_ruby(&mut annotated, surface, &reading, accent.as_deref());
         } else {
             annotated.push_str(&escape_html(surface));
         }
@@ -129,6 +357,10 @@ fn annotate_with_furigana(app: &AppHandle, text: &str) -> Result<String, String>
 }
 
 #[tauri::command]
-pub fn annotate_furigana(app: AppHandle, text: String) -> Result<String, String> {
-    annotate_with_furigana(&app, &text)
+pub fn annotate_furigana(
+    app: AppHandle,
+    text: String,
+    accent_mode: Option<bool>,
+) -> Result<String, String> {
+    annotate_with_furigana(&app, &text, accent_mode.unwrap_or(false))
 }
@@ -1,4 +1,6 @@
+use crate::cache::cache_db_path;
 use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
 use serde::Serialize;
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter};
@@ -13,30 +15,108 @@ pub struct HistoryEntry {
 
 static TRANSLATION_HISTORY: Lazy<Mutex<Vec<HistoryEntry>>> = Lazy::new(|| Mutex::new(Vec::new()));
 
-#[tauri::command]
-pub fn record_translation_history(app: AppHandle, original: String, translation: String) {
-    if original.trim().is_empty() || translation.trim().is_empty() {
-        return;
-    }
+fn init_history_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            original TEXT NOT NULL,
+            translation TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+            original, translation, content='history', content_rowid='id'
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS history_after_insert AFTER INSERT ON history BEGIN
+            INSERT INTO history_fts(rowid, original, translation)
+            VALUES (new.id, new.original, new.translation);
+        END",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Hydrates the in-memory front with the most recent rows from disk; call
+/// once at startup so `get_translation_history` has data before the first
+/// new entry is recorded.
+pub fn load_recent_history(app: &AppHandle) -> Result<(), String> {
+    let path = cache_db_path(app).map_err(|e| e.to_string())?;
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    init_history_schema(&conn).map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT original, translation FROM history ORDER BY id DESC LIMIT ?1")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query_map(params![MAX_HISTORY as i64], |row| {
+            Ok(HistoryEntry {
+                original: row.get(0)?,
+                translation: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    rows.reverse();
 
     let mut history = TRANSLATION_HISTORY
         .lock()
         .expect("translation history mutex poisoned");
-    history.push(HistoryEntry {
-        original,
-        translation,
-    });
-
-    if history.len() > MAX_HISTORY {
-        let overflow = history.len() - MAX_HISTORY;
-        history.drain(0..overflow);
+    *history = rows;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn record_translation_history(
+    app: AppHandle,
+    original: String,
+    translation: String,
+) -> Result<(), String> {
+    if original.trim().is_empty() || translation.trim().is_empty() {
+        return Ok(());
+    }
+
+    {
+        let mut history = TRANSLATION_HISTORY
+            .lock()
+            .expect("translation history mutex poisoned");
+        history.push(HistoryEntry {
+            original: original.clone(),
+            translation: translation.clone(),
+        });
+        if history.len() > MAX_HISTORY {
+            let overflow = history.len() - MAX_HISTORY;
+            history.drain(0..overflow);
+        }
     }
 
-    drop(history);
+    let path = cache_db_path(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<(), String> {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        init_history_schema(&conn).map_err(|e| e.to_string())?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs() as i64;
+        conn.execute(
+            "INSERT INTO history (original, translation, created_at) VALUES (?1, ?2, ?3)",
+            params![original, translation, now],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
 
     if let Err(err) = app.emit("translation_history_updated", ()) {
         eprintln!("[tauri] failed to emit translation_history_updated: {err}");
     }
+    Ok(())
 }
 
 #[tauri::command]
@@ -46,3 +126,41 @@ pub fn get_translation_history() -> Vec<HistoryEntry> {
         .expect("translation history mutex poisoned")
         .clone()
 }
+
+/// Full-text search over past translations via the `history_fts` FTS5 index.
+#[tauri::command]
+pub async fn search_translation_history(
+    app: AppHandle,
+    query: String,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>, String> {
+    let path = cache_db_path(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        init_history_schema(&conn).map_err(|e| e.to_string())?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT h.original, h.translation
+                FROM history_fts f
+                JOIN history h ON h.id = f.rowid
+                WHERE history_fts MATCH ?1
+                ORDER BY rank
+                LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![query, limit as i64], |row| {
+                Ok(HistoryEntry {
+                    original: row.get(0)?,
+                    translation: row.get(1)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok::<_, String>(rows)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
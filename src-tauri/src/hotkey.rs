@@ -0,0 +1,80 @@
+use crate::clipboard::poll_clipboard_text;
+use crate::language::should_emit;
+use crate::text_replacements::apply_text_replacements;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+/// The currently bound translate-on-demand chord, if any. Compared against
+/// every shortcut event so a stray callback from a just-unregistered
+/// accelerator can't fire a translation.
+static CURRENT_HOTKEY: Lazy<Mutex<Option<Shortcut>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Serialize)]
+pub struct HotkeyConflictPayload {
+    pub accelerator: String,
+    pub message: String,
+}
+
+/// Registered once as the global-shortcut plugin's handler in `main.rs`;
+/// dispatches every bound accelerator's press/release through here, so we
+/// filter to our own hotkey and ignore the release half of the chord.
+pub fn handle_shortcut_event(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    if CURRENT_HOTKEY.lock().unwrap().as_ref() != Some(shortcut) {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match poll_clipboard_text().await {
+            Ok(Some(text)) if !text.is_empty() => {
+                if !should_emit(&text) {
+                    return;
+                }
+                let processed = apply_text_replacements(&text);
+                if let Err(err) = app.emit("incoming_text", processed) {
+                    eprintln!("[tauri] failed to emit incoming_text from hotkey: {err}");
+                }
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("[tauri] hotkey clipboard poll failed: {err}"),
+        }
+    });
+}
+
+/// Binds `accelerator` as the translate-on-demand hotkey, replacing
+/// whatever was bound before. Always unregisters the previous chord first
+/// so re-registering the same accelerator (or swapping to a different one
+/// after a mistimed call) never collides with a stale binding. Registration
+/// conflicts (e.g. another app already owns the chord) are reported to the
+/// frontend as an event instead of propagating a panic.
+#[tauri::command]
+pub fn set_translate_hotkey(app: AppHandle, accelerator: String) -> Result<(), String> {
+    let shortcut: Shortcut = accelerator
+        .parse()
+        .map_err(|err| format!("invalid accelerator \"{accelerator}\": {err}"))?;
+
+    let previous = CURRENT_HOTKEY.lock().unwrap().take();
+    if let Some(previous) = previous {
+        let _ = app.global_shortcut().unregister(previous);
+    }
+
+    if let Err(err) = app.global_shortcut().register(shortcut) {
+        let payload = HotkeyConflictPayload {
+            accelerator: accelerator.clone(),
+            message: err.to_string(),
+        };
+        if let Err(emit_err) = app.emit("hotkey_register_failed", payload) {
+            eprintln!("[tauri] failed to notify frontend about hotkey conflict: {emit_err}");
+        }
+        return Err(err.to_string());
+    }
+
+    *CURRENT_HOTKEY.lock().unwrap() = Some(shortcut);
+    Ok(())
+}
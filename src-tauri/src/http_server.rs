@@ -1,23 +1,72 @@
+use crate::language::should_emit;
 use crate::text_replacements::{apply_text_replacements, emit_processed_text};
 use anyhow::{Error, Result};
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use futures_util::{stream, StreamExt};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
 use tauri::{AppHandle, Emitter};
 use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::time::timeout;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use uuid::Uuid;
 
 const DEFAULT_PORT: u16 = 17889;
+const TRANSLATION_TIMEOUT: Duration = Duration::from_secs(120);
 
 static OPENAI_COMPATIBLE_INPUT: AtomicBool = AtomicBool::new(false);
 static HTTP_SERVER_ERROR: Lazy<Mutex<Option<HttpServerErrorPayload>>> =
     Lazy::new(|| Mutex::new(None));
 
+/// The currently running listener, if any, so `restart_http_server` can ask
+/// it to drain in-flight requests before binding the replacement port.
+struct RunningServer {
+    shutdown: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+static RUNNING_SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+
+/// A pending `/v1/chat/completions` request waiting on the frontend to
+/// deliver its translation via `submit_translation_result`.
+enum PendingTranslation {
+    /// Non-streaming: accumulate deltas until `done`, then resolve the oneshot.
+    Single(Option<oneshot::Sender<String>>, String),
+    /// Streaming: forward each delta straight to the SSE body.
+    Stream(mpsc::UnboundedSender<String>),
+}
+
+static PENDING_TRANSLATIONS: Lazy<Mutex<HashMap<Uuid, PendingTranslation>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Shared secret gating `/submit` and `/v1/chat/completions`. `None` (the
+/// default) keeps the endpoints open, matching pre-auth behavior.
+static HTTP_API_KEY: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
 #[derive(Deserialize, Debug)]
 struct OpenAIChatCompletionRequest {
     messages: Vec<OpenAIMessage>,
+    #[serde(default)]
+    stream: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -52,6 +101,53 @@ pub struct HttpServerErrorPayload {
     pub message: String,
 }
 
+#[derive(Clone, Serialize)]
+struct TranslationRequestPayload {
+    id: String,
+    text: String,
+}
+
+/// Byte-for-byte comparison that, for inputs of equal length, inspects
+/// every byte before deciding, so a mismatched request can't learn how many
+/// leading bytes of the key it guessed correctly from response timing.
+/// Inputs of different lengths are rejected at once, so the key's length
+/// is not hidden.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Rejects requests with `401` when an API key is configured and the
+/// `Authorization: Bearer <key>` header is missing or doesn't match. Stays
+/// a no-op when no key has been set, so existing unauthenticated setups
+/// keep working.
+async fn require_api_key(req: Request, next: Next) -> Response {
+    let Some(expected) = HTTP_API_KEY.lock().unwrap().clone() else {
+        return next.run(req).await;
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token, &expected) => next.run(req).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+/// Sets the shared secret `/submit` and `/v1/chat/completions` require via
+/// `Authorization: Bearer <key>`. Pass `None` to return to open access.
+#[tauri::command]
+pub fn set_http_api_key(key: Option<String>) {
+    *HTTP_API_KEY.lock().unwrap() = key.filter(|k| !k.is_empty());
+}
+
 async fn submit(State(app): State<AppHandle>, body: String) -> impl axum::response::IntoResponse {
     println!("[tauri] received /submit, len={}", body.len());
     if let Err(err) = emit_processed_text(&app, &body) {
@@ -61,12 +157,23 @@ async fn submit(State(app): State<AppHandle>, body: String) -> impl axum::respon
     StatusCode::OK
 }
 
+/// Registers `id` in the pending-translation registry and schedules its
+/// removal after `TRANSLATION_TIMEOUT` so a frontend that never responds
+/// (closed window, crashed renderer) doesn't leak the sender forever.
+fn register_pending(id: Uuid, pending: PendingTranslation) {
+    PENDING_TRANSLATIONS.lock().unwrap().insert(id, pending);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(TRANSLATION_TIMEOUT).await;
+        PENDING_TRANSLATIONS.lock().unwrap().remove(&id);
+    });
+}
+
 async fn openai_chat_completions(
     State(app): State<AppHandle>,
     Json(payload): Json<OpenAIChatCompletionRequest>,
-) -> impl axum::response::IntoResponse {
+) -> Response {
     if !OPENAI_COMPATIBLE_INPUT.load(Ordering::Relaxed) {
-        return StatusCode::NOT_FOUND;
+        return StatusCode::NOT_FOUND.into_response();
     }
 
     let maybe_text = payload
@@ -78,20 +185,104 @@ async fn openai_chat_completions(
         .map(|t| t.trim().to_string())
         .filter(|t| !t.is_empty());
 
-    if let Some(text) = maybe_text {
-        println!(
-            "[tauri] received OpenAI-compatible /v1/chat/completions, len={}",
-            text.len()
-        );
-        let processed = apply_text_replacements(&text);
-        if let Err(err) = app.emit("incoming_text", processed) {
-            eprintln!("[tauri] failed to emit incoming_text from OpenAI-compatible input: {err}");
+    let Some(text) = maybe_text else {
+        eprintln!("[tauri] OpenAI-compatible request missing user message");
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    println!(
+        "[tauri] received OpenAI-compatible /v1/chat/completions, len={}",
+        text.len()
+    );
+
+    if !should_emit(&text) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let processed = apply_text_replacements(&text);
+
+    let id = Uuid::new_v4();
+
+    if payload.stream {
+        let (tx, rx) = mpsc::unbounded_channel::<String>();
+        register_pending(id, PendingTranslation::Stream(tx));
+
+        if let Err(err) = app.emit(
+            "incoming_translation_request",
+            TranslationRequestPayload {
+                id: id.to_string(),
+                text: processed,
+            },
+        ) {
+            eprintln!("[tauri] failed to emit incoming_translation_request: {err}");
         }
+
+        let deltas = UnboundedReceiverStream::new(rx).map(|delta| {
+            Ok::<Event, Infallible>(
+                Event::default().data(
+                    json!({"choices": [{"delta": {"content": delta}}]}).to_string(),
+                ),
+            )
+        });
+        let done = stream::once(async { Ok::<Event, Infallible>(Event::default().data("[DONE]")) });
+
+        Sse::new(deltas.chain(done)).into_response()
     } else {
-        eprintln!("[tauri] OpenAI-compatible request missing user message");
+        let (tx, rx) = oneshot::channel::<String>();
+        register_pending(id, PendingTranslation::Single(Some(tx), String::new()));
+
+        if let Err(err) = app.emit(
+            "incoming_translation_request",
+            TranslationRequestPayload {
+                id: id.to_string(),
+                text: processed,
+            },
+        ) {
+            eprintln!("[tauri] failed to emit incoming_translation_request: {err}");
+        }
+
+        match timeout(TRANSLATION_TIMEOUT, rx).await {
+            Ok(Ok(translation)) => Json(json!({
+                "choices": [{"message": {"role": "assistant", "content": translation}}]
+            }))
+            .into_response(),
+            _ => {
+                PENDING_TRANSLATIONS.lock().unwrap().remove(&id);
+                StatusCode::GATEWAY_TIMEOUT.into_response()
+            }
+        }
     }
+}
 
-    StatusCode::NOT_FOUND
+/// Called by the frontend as translation tokens for `id` arrive. `done`
+/// closes out the registration: a streamed response's SSE body ends, and a
+/// non-streaming response's oneshot resolves with the accumulated text.
+#[tauri::command]
+pub fn submit_translation_result(id: String, text: String, done: bool) -> Result<(), String> {
+    let id = Uuid::parse_str(&id).map_err(|err| format!("invalid translation id: {err}"))?;
+    let mut pending = PENDING_TRANSLATIONS.lock().unwrap();
+
+    match pending.get_mut(&id) {
+        Some(PendingTranslation::Stream(tx)) => {
+            if !text.is_empty() {
+                let _ = tx.send(text);
+            }
+            if done {
+                pending.remove(&id);
+            }
+        }
+        Some(PendingTranslation::Single(sender, accumulated)) => {
+            accumulated.push_str(&text);
+            if done {
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(std::mem::take(accumulated));
+                }
+                pending.remove(&id);
+            }
+        }
+        None => {}
+    }
+
+    Ok(())
 }
 
 fn extract_content_text(content: &OpenAIContent) -> Option<String> {
@@ -106,17 +297,54 @@ fn extract_content_text(content: &OpenAIContent) -> Option<String> {
     }
 }
 
-pub async fn start_http_server(app: AppHandle, port: u16) -> Result<()> {
+async fn start_http_server(app: AppHandle, port: u16, shutdown: Arc<Notify>) -> Result<()> {
     let app_router = Router::new()
         .route("/submit", post(submit))
         .route("/v1/chat/completions", post(openai_chat_completions))
+        .layer(middleware::from_fn(require_api_key))
         .with_state(app.clone());
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     let listener = TcpListener::bind(addr).await?;
 
     println!("[tauri] HTTP server listening on http://{addr}");
-    axum::serve(listener, app_router).await?;
+    axum::serve(listener, app_router)
+        .with_graceful_shutdown(async move { shutdown.notified().await })
+        .await?;
+    Ok(())
+}
+
+/// Binds `port` and records it as the running listener, replacing whatever
+/// `RUNNING_SERVER` held before (the caller is responsible for having
+/// already drained that one, see `restart_http_server`).
+pub fn spawn_http_server(app: AppHandle, port: u16) {
+    let shutdown = Arc::new(Notify::new());
+    let shutdown_for_task = shutdown.clone();
+    let app_for_task = app.clone();
+
+    let task = tauri::async_runtime::spawn(async move {
+        if let Err(err) = start_http_server(app_for_task.clone(), port, shutdown_for_task).await {
+            eprintln!("[tauri] failed to start HTTP listener: {err}");
+            record_http_error(&app_for_task, port, &err);
+        }
+    });
+
+    *RUNNING_SERVER.lock().unwrap() = Some(RunningServer { shutdown, task });
+}
+
+/// Signals the current listener to stop accepting new connections and
+/// drain in-flight ones, waits for it to exit, then binds `port`. Lets
+/// users change `TRANSLATOR_PORT` at runtime from settings without a full
+/// relaunch.
+#[tauri::command]
+pub async fn restart_http_server(app: AppHandle, port: u16) -> Result<(), String> {
+    let old = RUNNING_SERVER.lock().unwrap().take();
+    if let Some(old) = old {
+        old.shutdown.notify_one();
+        let _ = old.task.await;
+    }
+
+    spawn_http_server(app, port);
     Ok(())
 }
 
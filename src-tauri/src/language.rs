@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LANGUAGE_FILTER_ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn is_hiragana(c: char) -> bool {
+    ('\u{3040}'..='\u{309F}').contains(&c)
+}
+
+fn is_katakana(c: char) -> bool {
+    ('\u{30A0}'..='\u{30FF}').contains(&c)
+}
+
+fn is_han(c: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&c)
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphabetic()
+}
+
+/// Classifies text as Japanese by counting characters per Unicode block:
+/// any kana at all is a strong signal, while bare Han characters (e.g.
+/// Chinese) only count as Japanese when they are at least as many as the
+/// ASCII Latin letters alongside them; more Latin letters than Han suggests
+/// it's actually romanized/English text quoting a name.
+pub fn is_japanese_text(text: &str) -> bool {
+    let mut hiragana = 0usize;
+    let mut katakana = 0usize;
+    let mut han = 0usize;
+    let mut latin = 0usize;
+
+    for c in text.chars() {
+        if is_hiragana(c) {
+            hiragana += 1;
+        } else if is_katakana(c) {
+            katakana += 1;
+        } else if is_han(c) {
+            han += 1;
+        } else if is_latin(c) {
+            latin += 1;
+        }
+    }
+
+    if hiragana > 0 || katakana > 0 {
+        return true;
+    }
+
+    han > 0 && han >= latin
+}
+
+/// Whether the clipboard/HTTP ingestion paths should emit `text`; `false`
+/// means it is suppressed as non-Japanese noise. Returns `true` (never suppress) when the filter is
+/// disabled, so users translating from other languages aren't blocked.
+pub fn should_emit(text: &str) -> bool {
+    if !LANGUAGE_FILTER_ENABLED.load(Ordering::Relaxed) {
+        return true;
+    }
+    is_japanese_text(text)
+}
+
+#[tauri::command]
+pub fn set_language_filter(enabled: bool) {
+    LANGUAGE_FILTER_ENABLED.store(enabled, Ordering::Relaxed);
+}
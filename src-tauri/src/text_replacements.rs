@@ -1,3 +1,4 @@
+use crate::language::should_emit;
 use once_cell::sync::Lazy;
 use regex::{Regex, RegexBuilder};
 use std::sync::Mutex;
@@ -15,6 +16,15 @@ pub struct ReplacementRulePayload {
     pub replacement: String,
     #[serde(default)]
     pub flags: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Lower values run first; ties keep their original order.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 static TEXT_REPLACEMENTS: Lazy<Mutex<Vec<TextReplacementRule>>> =
@@ -45,10 +55,14 @@ fn build_regex(pattern: &str, flags: &str) -> Result<Regex, regex::Error> {
     builder.build()
 }
 
-fn compile_replacement_rules(rules: Vec<ReplacementRulePayload>) -> Vec<TextReplacementRule> {
+/// Drops disabled rules and orders the rest by `priority` (lower runs
+/// first, ties keep their original order) before compiling.
+fn compile_replacement_rules(mut rules: Vec<ReplacementRulePayload>) -> Vec<TextReplacementRule> {
+    rules.sort_by_key(|rule| rule.priority);
+
     let mut compiled = Vec::new();
     for rule in rules {
-        if rule.pattern.trim().is_empty() {
+        if !rule.enabled || rule.pattern.trim().is_empty() {
             continue;
         }
 
@@ -88,6 +102,9 @@ pub fn apply_text_replacements(raw: &str) -> String {
 }
 
 pub fn emit_processed_text(app: &AppHandle, raw: &str) -> Result<(), tauri::Error> {
+    if !should_emit(raw) {
+        return Ok(());
+    }
     let processed = apply_text_replacements(raw);
     app.emit("incoming_text", processed)
 }
@@ -99,3 +116,31 @@ pub fn set_text_replacements(rules: Vec<ReplacementRulePayload>) -> Result<(), S
     *storage = compiled;
     Ok(())
 }
+
+#[derive(serde::Serialize)]
+pub struct TestReplacementResult {
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Compiles a single rule against `sample` without touching the committed
+/// rule set, so the UI can preview a regex error or a `$name` named-group
+/// substitution before the user saves it.
+#[tauri::command]
+pub fn test_replacement(
+    pattern: String,
+    flags: String,
+    replacement: String,
+    sample: String,
+) -> TestReplacementResult {
+    match build_regex(&pattern, &flags) {
+        Ok(regex) => TestReplacementResult {
+            output: Some(regex.replace_all(&sample, replacement.as_str()).into_owned()),
+            error: None,
+        },
+        Err(err) => TestReplacementResult {
+            output: None,
+            error: Some(err.to_string()),
+        },
+    }
+}